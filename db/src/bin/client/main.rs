@@ -18,11 +18,14 @@
 
 extern crate db;
 
-use std::sync::Arc;
-use std::cell::Cell;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::fmt::Display;
 use std::str::FromStr;
 use std::mem::size_of;
+use std::mem::transmute;
 use std::net::Ipv4Addr;
 
 use db::e2d2::headers::*;
@@ -32,8 +35,10 @@ use db::e2d2::scheduler::NetBricksContext as NetbricksContext;
 use db::e2d2::common::EmptyMetadata;
 use db::e2d2::config::{NetbricksConfiguration, PortConfiguration};
 use db::config;
+use db::cycles;
 use db::log::*;
-use db::wireformat::{GetRequest, InvokeRequest};
+use db::rpc::{parse_rpc_opcode, OpCode, RpcStatus};
+use db::wireformat::{GetRequest, GetResponse, InvokeRequest, InvokeResponse};
 
 // Type aliases for convenience.
 type UdpPacket = Packet<UdpHeader, EmptyMetadata>;
@@ -41,6 +46,179 @@ type IpPacket = Packet<IpHeader, EmptyMetadata>;
 
 mod ycsb;
 
+// Significant-figure bits for `Histogram`: each exponent is split into
+// `2^HIST_SIG_FIGS` linear sub-buckets, trading memory for precision. At 7 a
+// recorded cycle count keeps its top seven bits, so the value reported for it
+// is less than one part in 64 below the recorded one.
+const HIST_SIG_FIGS: u32 = 7;
+
+/// Shared statistics across all per-core request generators. Each generator
+/// owns one slot (indexed by its shard id) and publishes a snapshot of its
+/// latency histogram and received count into it once per reporting interval;
+/// the master shard merges every slot to produce a single cluster-wide report.
+struct ClientStats {
+    // Per-core latency histogram snapshots, refreshed at report time.
+    per_core: Vec<Mutex<Histogram>>,
+
+    // Per-core count of responses received so far.
+    recvd: Vec<AtomicU64>,
+}
+
+impl ClientStats {
+    /// Allocates statistics slots for `cores` request generators.
+    fn new(cores: usize) -> ClientStats {
+        let mut per_core = Vec::with_capacity(cores);
+        let mut recvd = Vec::with_capacity(cores);
+        for _ in 0..cores {
+            per_core.push(Mutex::new(Histogram::new()));
+            recvd.push(AtomicU64::new(0));
+        }
+        ClientStats {
+            per_core: per_core,
+            recvd: recvd,
+        }
+    }
+}
+
+/// Precomputed per-endpoint headers for one storage server. Keeping a full set
+/// of templates per endpoint lets `create_request()` stay a pure copy even when
+/// requests are sprayed across a cluster.
+#[derive(Clone)]
+struct Endpoint {
+    // MAC header addressed to this server.
+    mac: MacHeader,
+
+    // IP header addressed to this server.
+    ip: IpHeader,
+
+    // UDP header addressed to this server's receive port.
+    udp: UdpHeader,
+}
+
+/// A consistent-hashing ring mapping record keys onto storage endpoints. Each
+/// endpoint owns `vnodes` virtual nodes scattered across a 64-bit space so that
+/// keys are balanced even when the cluster is small or heterogeneous.
+struct Ring {
+    // The endpoints participating in the ring.
+    endpoints: Vec<Endpoint>,
+
+    // Virtual nodes sorted by hash, each carrying the index of its endpoint.
+    vnodes: Vec<(u64, usize)>,
+}
+
+impl Ring {
+    /// Builds a ring placing `vnodes` virtual nodes per endpoint.
+    fn new(endpoints: Vec<Endpoint>, vnodes: usize) -> Ring {
+        let mut v: Vec<(u64, usize)> = Vec::with_capacity(endpoints.len() * vnodes);
+        for (i, _ep) in endpoints.iter().enumerate() {
+            for replica in 0..vnodes {
+                // Hash the endpoint id together with the replica index so each
+                // server lands at `vnodes` independent points on the ring.
+                v.push((hash_u64((i as u64) << 32 | replica as u64), i));
+            }
+        }
+        v.sort_by_key(|&(h, _)| h);
+
+        Ring {
+            endpoints: endpoints,
+            vnodes: v,
+        }
+    }
+
+    /// Returns the endpoint owning `key` — the first virtual node clockwise of
+    /// the key's hash, wrapping around the ring.
+    #[inline]
+    fn owner(&self, key: u64) -> &Endpoint {
+        let h = hash_u64(key);
+        let idx = match self.vnodes.binary_search_by_key(&h, |&(vh, _)| vh) {
+            Ok(i) => i,
+            Err(i) => if i == self.vnodes.len() { 0 } else { i },
+        };
+        &self.endpoints[self.vnodes[idx].1]
+    }
+}
+
+/// A 64-bit mix (splitmix64 finalizer) used to place endpoints and keys on the
+/// consistent-hashing ring.
+#[inline]
+fn hash_u64(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// A fixed-memory, log-scaled latency histogram à la HDR.
+///
+/// Each recorded value is placed into an exponent bucket given by the position
+/// of its highest set bit, and then into one of `2^HIST_SIG_FIGS` equal-width
+/// sub-buckets within that exponent. Recording is O(1) and memory is bounded
+/// regardless of how many samples are accumulated.
+#[derive(Clone)]
+struct Histogram {
+    // Flat count array indexed by `bucket * sub_bucket_count + sub_bucket`.
+    counts: Vec<u64>,
+
+    // Number of sub-buckets per exponent bucket (a power of two).
+    sub_bucket_count: u64,
+
+    // Total number of samples recorded so far.
+    total: u64,
+}
+
+impl Histogram {
+    /// Returns an empty histogram sized to hold values up to 64 bits wide.
+    fn new() -> Histogram {
+        let sub_bucket_count = 1u64 << HIST_SIG_FIGS;
+        Histogram {
+            // 64 exponent buckets is enough for any u64 cycle count.
+            counts: vec![0; (64 * sub_bucket_count) as usize],
+            sub_bucket_count: sub_bucket_count,
+            total: 0,
+        }
+    }
+
+    /// Records a single value (in cycles) in O(1).
+    #[inline]
+    fn record(&mut self, value: u64) {
+        let bucket = 64u32.saturating_sub(value.leading_zeros());
+        let bucket = bucket.saturating_sub(HIST_SIG_FIGS) as u64;
+        let sub = (value >> bucket) & (self.sub_bucket_count - 1);
+        let idx = (bucket * self.sub_bucket_count + sub) as usize;
+        self.counts[idx] += 1;
+        self.total += 1;
+    }
+
+    /// Adds the counts of `other` into this histogram bucket-wise. Used to
+    /// combine the per-core histograms into one before computing percentiles.
+    fn merge(&mut self, other: &Histogram) {
+        for (dst, &src) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *dst += src;
+        }
+        self.total += other.total;
+    }
+
+    /// Returns the value at percentile `q` (0.0..=1.0) by walking cumulative
+    /// counts and reconstructing the representative value of the bucket that
+    /// crosses the target rank.
+    fn percentile(&self, q: f64) -> u64 {
+        if self.total == 0 {
+            return 0;
+        }
+
+        let target = (q * self.total as f64) as u64;
+        let mut cumulative = 0u64;
+        for (idx, &count) in self.counts.iter().enumerate() {
+            cumulative += count;
+            if cumulative >= target {
+                let bucket = idx as u64 / self.sub_bucket_count;
+                let sub = idx as u64 % self.sub_bucket_count;
+                return sub << bucket;
+            }
+        }
+        0
+    }
+}
+
 /// This type implements a simple request generator for Sandstorm.
 /// When the generate_request() method on this type is called, an RPC request
 /// is created and sent out over a network interface.
@@ -55,18 +233,103 @@ where
     // the requests generated will be regular get() RPCs.
     use_invoke: bool,
 
-    // The UDP header on each packet generated by the request generator.
-    req_udp_header: UdpHeader,
-
-    // The IP header on each packet generated by the request generator.
-    // Currently using IPv4.
-    req_ip_header: IpHeader,
-
-    // The MAC header on each packet generated by the request generator.
-    req_mac_header: MacHeader,
+    // Consistent-hashing ring of server endpoints. The owning endpoint of each
+    // request's key supplies the MAC/IP/UDP headers placed on that packet.
+    ring: Ring,
 
     // Tracks number of packets sent to the server for occasional debug messages.
     requests_sent: Cell<u64>,
+
+    // Target offered load in requests per second. If zero, the generator runs
+    // in closed-loop mode and emits one request on every scheduler tick. If
+    // positive, requests are paced as a Poisson arrival process.
+    rate: f64,
+
+    // The cycle count at which the next request is due to be sent out. Used to
+    // pace the generator in open-loop mode; interpreted as an absolute value of
+    // `cycles::rdtsc()`.
+    next_send: Cell<u64>,
+
+    // Number of send deadlines that had already elapsed by the time they were
+    // observed. A non-zero value indicates the generator cannot sustain the
+    // configured `rate` (open-loop overload).
+    missed: Cell<u64>,
+
+    // State of the xorshift generator used to draw exponentially distributed
+    // inter-arrival gaps. Seeded once in `new()`.
+    rng: Cell<u64>,
+
+    // Monotonically increasing request id stamped into every outgoing packet
+    // and echoed back by the server. Used to match responses to requests.
+    next_req_id: Cell<u64>,
+
+    // Outstanding requests keyed by request id, each mapped to the cycle count
+    // at which it was sent. Required to compute completion latency and to
+    // detect requests whose reply never arrives.
+    outstanding: RefCell<HashMap<u64, u64>>,
+
+    // Log-scaled histogram of completion latencies, in cycles.
+    latencies: RefCell<Histogram>,
+
+    // Number of replies received so far. Used to estimate achieved throughput.
+    responses_rcvd: Cell<u64>,
+
+    // Number of requests whose reply did not arrive within `read_timeout`.
+    losses: Cell<u64>,
+
+    // Per-request deadline, in cycles. A request without a reply after this many
+    // cycles have elapsed since it was sent is counted as a loss.
+    read_timeout: u64,
+
+    // Cycle count of the most recent periodic latency report.
+    last_report: Cell<u64>,
+
+    // Cycle count at which the generator started running. Used together with
+    // `responses_rcvd` to estimate achieved throughput.
+    start: Cell<u64>,
+
+    // If true, the generator runs in adaptive closed-loop mode: the number of
+    // outstanding requests is bounded by a TCP NewReno style congestion window
+    // that grows on acks and shrinks on loss, letting the client probe the
+    // server's saturation point automatically.
+    adaptive: bool,
+
+    // Current congestion window: the maximum number of unacked requests allowed
+    // in flight. Fractional so that congestion avoidance can add ~1 per RTT.
+    cwnd: Cell<f64>,
+
+    // Slow-start threshold. While `cwnd < ssthresh` the window grows by one per
+    // ack (slow start); beyond it the window grows by `ca_increment / cwnd` per
+    // ack (congestion avoidance).
+    ssthresh: Cell<f64>,
+
+    // Additive-increase constant applied per ack during congestion avoidance.
+    ca_increment: f64,
+
+    // Multiplicative-decrease factor applied to the window on a detected loss.
+    md_factor: f64,
+
+    // NewReno recovery marker: the highest request id sent when the window was
+    // last reduced. A loss whose id is at or below this marker belongs to the
+    // same round-trip as a reduction already taken, so it is ignored — the
+    // window is cut at most once per RTT rather than once per lost packet.
+    recover: Cell<u64>,
+
+    // Draws record keys following the configured YCSB distribution.
+    keygen: ycsb::KeyGenerator,
+
+    // The YCSB read/write mix driving whether each request is a get or a put.
+    workload: ycsb::Workload,
+
+    // This generator's shard id, equal to its position in the configured core
+    // set. Used to carve out a disjoint request-id space so replies
+    // demultiplex cleanly back to the core that issued them. Tenant ids are
+    // not sharded: every request is issued as tenant 1.
+    shard: usize,
+
+    // Shared per-core statistics. This generator publishes into slot `shard`;
+    // the master shard (0) merges all slots for the periodic report.
+    stats: Arc<ClientStats>,
 }
 
 impl<T> RequestGenerator<T>
@@ -76,63 +339,170 @@ where
     /// This function returns an instance of RequestGenerator. The RPC, UDP, IP,
     /// and MAC headers on packets generated by this instance are pre-computed
     /// in this method.
-    fn new(config: &config::ClientConfig, port: T) -> RequestGenerator<T> {
-        // Create UDP, IP, and MAC headers that are placed on all outgoing packets.
-        // Length fields are tweaked on a request-by-request basis in the outgoing
-        // packets.
-        let mut udp_header: UdpHeader = UdpHeader::new();
-        udp_header.set_src_port(config.udp_port);
-        udp_header.set_dst_port(config.server_udp_port);
-        udp_header.set_length(8);
-        udp_header.set_checksum(0);
-
-        // Create a common ip header.
+    fn new(
+        config: &config::ClientConfig,
+        port: T,
+        shard: usize,
+        stats: Arc<ClientStats>,
+    ) -> RequestGenerator<T> {
+        // The source address is common to every outgoing packet; only the
+        // destination varies per endpoint.
         let ip_src_addr: u32 =
             u32::from(Ipv4Addr::from_str(&config.ip_address).expect("Failed to create source IP."));
-        let ip_dst_addr: u32 = u32::from(
-            Ipv4Addr::from_str(&config.server_ip_address)
-                .expect("Failed to create destination IP."),
-        );
+        let mac_src = config.parse_mac();
 
-        let mut ip_header: IpHeader = IpHeader::new();
-        ip_header.set_src(ip_src_addr);
-        ip_header.set_dst(ip_dst_addr);
-        ip_header.set_ttl(128);
-        ip_header.set_version(4);
-        ip_header.set_ihl(5);
-        ip_header.set_length(20);
+        // Build one header template per server endpoint. Length fields are
+        // tweaked on a request-by-request basis in the outgoing packets.
+        let mut endpoints: Vec<Endpoint> = Vec::with_capacity(config.server_endpoints.len());
+        for server in config.server_endpoints.iter() {
+            let mut udp_header: UdpHeader = UdpHeader::new();
+            udp_header.set_src_port(config.udp_port);
+            udp_header.set_dst_port(server.udp_port);
+            udp_header.set_length(8);
+            udp_header.set_checksum(0);
 
-        // Create a common mac header.
-        let mut mac_header: MacHeader = MacHeader::new();
-        mac_header.src = config.parse_mac();
-        mac_header.dst = config.parse_server_mac();
-        mac_header.set_etype(0x0800);
+            let ip_dst_addr: u32 = u32::from(
+                Ipv4Addr::from_str(&server.ip_address).expect("Failed to create destination IP."),
+            );
+
+            let mut ip_header: IpHeader = IpHeader::new();
+            ip_header.set_src(ip_src_addr);
+            ip_header.set_dst(ip_dst_addr);
+            ip_header.set_ttl(128);
+            ip_header.set_version(4);
+            ip_header.set_ihl(5);
+            ip_header.set_length(20);
+
+            let mut mac_header: MacHeader = MacHeader::new();
+            mac_header.src = mac_src;
+            mac_header.dst = server.parse_mac();
+            mac_header.set_etype(0x0800);
+
+            endpoints.push(Endpoint {
+                mac: mac_header,
+                ip: ip_header,
+                udp: udp_header,
+            });
+        }
 
         warn!("use_invoke: {}", config.use_invoke);
+        warn!("req_rate: {}", config.req_rate);
+        warn!("server endpoints: {}", endpoints.len());
 
         RequestGenerator {
             net_port: port.clone(),
             // If true, invoke() RPC requests will be generated. If false,
             // regular get() RPCs will be generated.
             use_invoke: config.use_invoke,
-            req_udp_header: udp_header,
-            req_ip_header: ip_header,
-            req_mac_header: mac_header,
+            ring: Ring::new(endpoints, config.vnodes_per_server),
             requests_sent: Cell::new(0),
+            rate: config.req_rate as f64,
+            // The first request is due immediately; the deadline is advanced by
+            // an exponential gap each time a request is emitted.
+            next_send: Cell::new(cycles::rdtsc()),
+            missed: Cell::new(0),
+            rng: Cell::new(0x2545_f491_4f6c_dd1d ^ cycles::rdtsc()),
+            // Carve out a disjoint request-id range per shard (top 16 bits hold
+            // the shard id) so that responses always demux back to the core
+            // that sent them.
+            next_req_id: Cell::new((shard as u64) << 48),
+            outstanding: RefCell::new(HashMap::new()),
+            latencies: RefCell::new(Histogram::new()),
+            responses_rcvd: Cell::new(0),
+            losses: Cell::new(0),
+            // Convert the configured read timeout (in microseconds) to cycles.
+            read_timeout: (config.read_timeout as f64 * 1e-6
+                * cycles::cycles_per_second() as f64) as u64,
+            last_report: Cell::new(cycles::rdtsc()),
+            start: Cell::new(cycles::rdtsc()),
+            adaptive: config.adaptive,
+            cwnd: Cell::new(config.cwnd_init as f64),
+            ssthresh: Cell::new(config.ssthresh_init as f64),
+            ca_increment: config.ca_increment,
+            md_factor: config.md_factor,
+            recover: Cell::new(0),
+            keygen: ycsb::KeyGenerator::new(
+                config.n_keys,
+                ycsb::Distribution::parse(&config.key_distribution),
+                config.skew,
+                cycles::rdtsc(),
+            ),
+            workload: ycsb::Workload::from_name(&config.workload),
+            shard: shard,
+            stats: stats,
         }
     }
 
+    /// Grows the congestion window in response to a single acked reply.
+    /// Exponential growth during slow start, additive (~+1 per RTT) afterwards.
+    #[inline]
+    fn on_ack(&self) {
+        let cwnd = self.cwnd.get();
+        let cwnd = if cwnd < self.ssthresh.get() {
+            cwnd + 1.0
+        } else {
+            cwnd + self.ca_increment / cwnd
+        };
+        self.cwnd.set(cwnd);
+    }
+
+    /// Shrinks the congestion window in response to a loss (a reply timeout or
+    /// an explicit server-overload status), NewReno multiplicative decrease.
+    /// `id` is the request id that triggered the loss; losses from the same
+    /// round-trip as a reduction already taken are ignored so the window is cut
+    /// at most once per RTT instead of once per lost packet.
+    #[inline]
+    fn on_loss(&self, id: u64) {
+        if id <= self.recover.get() {
+            return;
+        }
+        // Everything sent so far belongs to this loss episode; a later loss must
+        // clear this marker before it can cut the window again.
+        self.recover.set(self.next_req_id.get().saturating_sub(1));
+
+        // Halve the window (by `md_factor`) but never drop below one in-flight
+        // request, then resume congestion avoidance from the new threshold.
+        let w = (self.cwnd.get() * self.md_factor).max(1.0);
+        self.ssthresh.set(w);
+        self.cwnd.set(w);
+    }
+
+    /// Draws the next uniformly distributed value in (0, 1] from the xorshift
+    /// generator backing the Poisson arrival process.
+    #[inline]
+    fn next_uniform(&self) -> f64 {
+        // Marsaglia's xorshift64, advanced in place through the `Cell`.
+        let mut x = self.rng.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng.set(x);
+
+        // Map into (0, 1]; adding one to the numerator keeps the value away from
+        // zero so that -ln(U) stays finite.
+        ((x >> 11) as f64 + 1.0) / (((1u64 << 53) as f64) + 1.0)
+    }
+
+    /// Advances `next_send` by one exponentially distributed inter-arrival gap
+    /// so that emitted requests follow a Poisson process at `self.rate`.
+    #[inline]
+    fn advance_deadline(&self) {
+        let gap = -(self.next_uniform().ln()) / self.rate;
+        let gap = (gap * cycles::cycles_per_second() as f64) as u64;
+        self.next_send.set(self.next_send.get() + gap);
+    }
+
     /// Allocate a packet and push MAC, IP, and UDP headers on it taken
     /// from the server desination specificated in `new()`. Panics
     /// if allocation or header manipulation fails at any point.
     #[inline]
-    fn create_request(&self) -> UdpPacket {
+    fn create_request(&self, endpoint: &Endpoint) -> UdpPacket {
         new_packet().expect("Failed to allocate packet for request!")
-            .push_header(&self.req_mac_header)
+            .push_header(&endpoint.mac)
             .expect("Failed to push MAC header into request!")
-            .push_header(&self.req_ip_header)
+            .push_header(&endpoint.ip)
             .expect("Failed to push IP header into request!")
-            .push_header(&self.req_udp_header)
+            .push_header(&endpoint.udp)
             .expect("Failed to push UDP header into request!")
     }
 
@@ -166,7 +536,9 @@ where
     fn create_get_request(&self,
                           tenant: u32,
                           table_id: u64,
-                          key: &[u8])
+                          key: &[u8],
+                          stamp: u64,
+                          endpoint: &Endpoint)
         -> IpPacket
     {
         if key.len() > u16::max_value() as usize {
@@ -174,10 +546,14 @@ where
             panic!("Key too long ({} bytes).", key.len());
         }
 
-        let mut request = self.create_request()
+        let mut request = self.create_request(endpoint)
                                 .push_header(&GetRequest::new(tenant, table_id, key.len() as u16))
                                 .expect("Failed to push RPC header into request!");
 
+        // Stamp the request id into the RPC header; the server copies it into
+        // the response so the receive path can match the reply by `stamp`.
+        request.get_mut_header().common_header.stamp = stamp;
+
         request.add_to_payload_tail(key.len(), &key)
                 .expect("Failed to write key into get() request!");
 
@@ -201,7 +577,9 @@ where
                              tenant: u32,
                              name_len: usize,
                              args_len: usize,
-                             payload: &[u8])
+                             payload: &[u8],
+                             stamp: u64,
+                             endpoint: &Endpoint)
         -> IpPacket
     {
         if name_len > u32::max_value() as usize {
@@ -214,11 +592,15 @@ where
             panic!("Args too long ({} bytes).", args_len);
         }
 
-        let mut request = self.create_request()
+        let mut request = self.create_request(endpoint)
                                 .push_header(&InvokeRequest::new(tenant, name_len as u32,
                                                                  args_len as u32))
                                 .expect("Failed to push RPC header into request!");
 
+        // Stamp the request id into the RPC header; the server copies it into
+        // the response so the receive path can match the reply by `stamp`.
+        request.get_mut_header().common_header.stamp = stamp;
+
         request.add_to_payload_tail(payload.len(), &payload)
                 .expect("Failed to write args into invoke() request!");
 
@@ -229,17 +611,46 @@ where
     /// out the network interface.
     #[inline]
     fn generate_request(&self) {
-        let request = if self.use_invoke {
+        // Draw a fresh, monotonically increasing request id. It is stamped into
+        // the RPC header (`common_header.stamp`) rather than into the key, since
+        // the server echoes the header stamp back on the reply but returns the
+        // stored value — not the request key — in the payload. Matching on the
+        // stamp is how the rest of the codebase (e.g. the pushback client) pairs
+        // replies with requests.
+        let id = self.next_req_id.get();
+        self.next_req_id.set(id + 1);
+        let send = cycles::rdtsc();
+
+        // Draw a record key from the YCSB distribution and place it in the low
+        // eight bytes of the key so that it determines which record is hit.
+        let record = self.keygen.next_key();
+
+        let mut key: [u8; 30] = [0; 30];
+        key[0..8].copy_from_slice(&unsafe { transmute::<u64, [u8; 8]>(record.to_le()) });
+
+        self.outstanding.borrow_mut().insert(id, send);
+
+        // Hash the record key onto the ring to find its owning endpoint, whose
+        // precomputed headers address the packet to the right server.
+        let endpoint = self.ring.owner(record);
+
+        // Issue the workload's reads as get() RPCs and the rest as invoke() RPCs
+        // of the `get` extension on the same key, mixed per the configured YCSB
+        // read percentage; no request writes a value. A zero read percentage or the legacy
+        // `use_invoke` flag forces every request through invoke().
+        let is_read =
+            !self.use_invoke && ((self.next_uniform() * 100.0) as usize) < self.workload.read_pct;
+
+        let request = if is_read {
+                self.create_get_request(1, 1, &key, id, endpoint)
+            } else {
                 let mut payload: Vec<u8> = Vec::new();
                 let table = [1, 0, 0, 0, 0, 0, 0, 0];
-                let key: [u8; 30] = [0; 30];
                 let name = "get".as_bytes();
                 payload.extend_from_slice(name);
                 payload.extend_from_slice(&table);
                 payload.extend_from_slice(&key);
-                self.create_invoke_request(1, name.len(), payload.len() - name.len(), payload.as_slice())
-            } else {
-                self.create_get_request(1, 1, &[0; 30])
+                self.create_invoke_request(1, name.len(), payload.len() - name.len(), payload.as_slice(), id, endpoint)
             };
 
         // Send the request out the network.
@@ -267,6 +678,148 @@ where
         self.requests_sent.set(r + 1);
 
     }
+
+    /// Drains the network port, matching each response back to its outstanding
+    /// request by the echoed request id and recording completion latency.
+    #[inline]
+    fn receive(&self) {
+        let mut mbufs: [*mut MBuf; 32] = [std::ptr::null_mut(); 32];
+
+        let rcvd = match self.net_port.recv(&mut mbufs) {
+            Ok(rcvd) => rcvd as usize,
+            Err(ref err) => {
+                println!("Error on packet recv: {}", err);
+                return;
+            }
+        };
+
+        let now = cycles::rdtsc();
+        for mbuf in mbufs.iter().take(rcvd) {
+            // Peel the MAC, IP, and UDP headers back off the received buffer to
+            // reach the RPC response payload.
+            let packet = unsafe { packet_from_mbuf_no_increment(*mbuf, 0) }
+                .parse_header::<MacHeader>()
+                .parse_header::<IpHeader>()
+                .parse_header::<UdpHeader>();
+
+            // The server echoes the request id back in the RPC header stamp.
+            let (id, overloaded) = match parse_rpc_opcode(&packet) {
+                OpCode::SandstormInvokeRpc => {
+                    let p = packet.parse_header::<InvokeResponse>();
+                    let id = p.get_header().common_header.stamp;
+                    // A non-Ok status is the server telling us it is pushing
+                    // back; treat it as a congestion signal.
+                    let overloaded = p.get_header().common_header.status != RpcStatus::StatusOk;
+                    p.free_packet();
+                    (id, overloaded)
+                }
+
+                OpCode::SandstormGetRpc => {
+                    let p = packet.parse_header::<GetResponse>();
+                    let id = p.get_header().common_header.stamp;
+                    let overloaded = p.get_header().common_header.status != RpcStatus::StatusOk;
+                    p.free_packet();
+                    (id, overloaded)
+                }
+
+                _ => {
+                    packet.free_packet();
+                    continue;
+                }
+            };
+
+            // Match the response to its outstanding request and record latency.
+            if let Some(send) = self.outstanding.borrow_mut().remove(&id) {
+                self.latencies.borrow_mut().record(now - send);
+                self.responses_rcvd.set(self.responses_rcvd.get() + 1);
+                if self.adaptive {
+                    if overloaded {
+                        self.on_loss(id);
+                    } else {
+                        self.on_ack();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Counts and retires requests whose reply did not arrive within
+    /// `read_timeout`. In adaptive mode a timeout also shrinks the congestion
+    /// window through `on_loss()`.
+    #[inline]
+    fn sweep_timeouts(&self) {
+        if self.read_timeout == 0 {
+            return;
+        }
+
+        let now = cycles::rdtsc();
+        let mut outstanding = self.outstanding.borrow_mut();
+        let before = outstanding.len();
+        // Track the newest id that timed out so the window reduction is attributed
+        // to the most recent loss episode.
+        let mut newest_lost = 0u64;
+        outstanding.retain(|&id, &mut send| {
+            let kept = now.saturating_sub(send) < self.read_timeout;
+            if !kept && id > newest_lost {
+                newest_lost = id;
+            }
+            kept
+        });
+        let lost = (before - outstanding.len()) as u64;
+        if lost > 0 {
+            self.losses.set(self.losses.get() + lost);
+            // A timeout is a loss signal for the adaptive window.
+            if self.adaptive {
+                self.on_loss(newest_lost);
+            }
+        }
+    }
+
+    /// Periodically logs p50/p99/p99.9 latency and achieved throughput.
+    #[inline]
+    fn report(&self) {
+        let now = cycles::rdtsc();
+        let elapsed = now - self.last_report.get();
+        // Report roughly once per second of wall-clock time.
+        if elapsed < cycles::cycles_per_second() {
+            return;
+        }
+        self.last_report.set(now);
+
+        // Publish this core's latest histogram snapshot and received count so
+        // the master shard can merge across cores.
+        *self.stats.per_core[self.shard].lock().unwrap() = self.latencies.borrow().clone();
+        self.stats.recvd[self.shard].store(self.responses_rcvd.get(), Ordering::Relaxed);
+
+        // Only the master shard emits the aggregated, cluster-wide report.
+        if self.shard != 0 {
+            return;
+        }
+
+        let mut merged = Histogram::new();
+        let mut total_rcvd = 0u64;
+        for (i, slot) in self.stats.per_core.iter().enumerate() {
+            merged.merge(&slot.lock().unwrap());
+            total_rcvd += self.stats.recvd[i].load(Ordering::Relaxed);
+        }
+
+        // Latency and throughput cover every shard; the loss count is this
+        // master shard's own.
+        info!(
+            "thrpt {:.0} op/s | p50 {:.1} p99 {:.1} p99.9 {:.1} ns | losses {}",
+            total_rcvd as f64 / cycles::to_seconds(now - self.start()),
+            cycles::to_seconds(merged.percentile(0.50)) * 1e9,
+            cycles::to_seconds(merged.percentile(0.99)) * 1e9,
+            cycles::to_seconds(merged.percentile(0.999)) * 1e9,
+            self.losses.get()
+        );
+    }
+
+    /// Cycle count at which this generator started, used for throughput.
+    #[inline]
+    fn start(&self) -> u64 {
+        self.start.get()
+    }
 }
 
 // Implementation of the Executable trait for RequestGenerator. This trait
@@ -281,7 +834,50 @@ where
     /// constantly invokes this method, effectively resulting in requests
     /// being sent out the network.
     fn execute(&mut self) {
-        self.generate_request();
+        // Drain any replies, retire timed-out requests, and periodically report
+        // latency and throughput before generating more load.
+        self.receive();
+        self.sweep_timeouts();
+        self.report();
+
+        // Adaptive closed-loop mode: keep the in-flight window full up to the
+        // current congestion window, letting acks and losses size the offered
+        // load automatically.
+        if self.adaptive {
+            while (self.outstanding.borrow().len() as f64) < self.cwnd.get() {
+                self.generate_request();
+            }
+            return;
+        }
+
+        // Closed-loop mode: fire a request on every scheduler tick.
+        if self.rate <= 0.0 {
+            self.generate_request();
+            return;
+        }
+
+        // Open-loop mode: emit one request per elapsed arrival deadline, pacing
+        // the stream as a Poisson process. A deadline that is already well in
+        // the past when observed is counted as a missed deadline so that the
+        // generator can report when it cannot sustain `self.rate`.
+        let now = cycles::rdtsc();
+        let mut emitted = 0u64;
+        while now >= self.next_send.get() {
+            self.generate_request();
+            self.advance_deadline();
+            emitted += 1;
+        }
+
+        // Every deadline beyond the first that was already due on this tick is a
+        // deadline we could not meet on time: the core is behind the offered
+        // load and the stream is no longer a faithful Poisson process.
+        if emitted > 1 {
+            let m = self.missed.get() + emitted - 1;
+            self.missed.set(m);
+            if m & 0xfffff == 0 {
+                warn!("Open-loop overload: {} missed send deadlines.", m);
+            }
+        }
     }
 
     /// This method returns a vector of tasks that need to be executed by
@@ -296,23 +892,35 @@ where
 
 /// This function adds a request generator (RequestGenerator) to a Netbricks
 /// pipeline. This function is passed in as a closure to Netbricks, and gets
-/// run once on each Netbricks scheduler during setup.
-fn setup_client<T, S>(config: &config::ClientConfig, ports: Vec<T>, scheduler: &mut S)
-where
+/// run once on each Netbricks scheduler during setup. Each scheduler owns one
+/// tx/rx queue and a distinct shard id (its order of invocation), so the
+/// generators share the offered load with disjoint request-id spaces.
+fn setup_client<T, S>(
+    config: &config::ClientConfig,
+    ports: Vec<T>,
+    scheduler: &mut S,
+    stats: Arc<ClientStats>,
+    next_shard: Arc<AtomicUsize>,
+) where
     T: PacketTx + PacketRx + Display + Clone + 'static,
     S: Scheduler + Sized,
 {
+    // Each scheduler is handed exactly one queue; the queue count scales with
+    // the configured core set rather than being pinned to one.
     if ports.len() != 1 {
-        println!("ERROR: Client should be configured with exactly 1 port!");
+        println!("ERROR: Each client scheduler should own exactly 1 port!");
         std::process::exit(1);
     }
 
-    let client: RequestGenerator<T> = RequestGenerator::new(config, ports[0].clone());
+    let shard = next_shard.fetch_add(1, Ordering::SeqCst);
+
+    let client: RequestGenerator<T> =
+        RequestGenerator::new(config, ports[0].clone(), shard, Arc::clone(&stats));
 
     // Add the request generator to a netbricks pipeline.
     match scheduler.add_task(client) {
         Ok(_) => {
-            println!("Successfully added client to a Netbricks pipeline.");
+            println!("Successfully added client shard {} to a Netbricks pipeline.", shard);
         }
 
         Err(ref err) => {
@@ -326,19 +934,21 @@ where
 /// initialize Netbricks with a default set of parameters.
 ///
 /// If used to initialize Netbricks, this struct will run the parent client
-/// thread on core 0, and one scheduler on core 1. Packet buffers will be
-/// allocated from a 2 GB memory pool, with 64 MB cached at core 1. DPDK will
-/// be initialized as a primary process without any additional arguments. A
-/// single network interface/port with 1 transmit queue, 1 receive queue, 256
-/// receive descriptors, and 256 transmit descriptors will be made available to
-/// Netbricks. Loopback, hardware transmit segementation offload, and hardware
-/// checksum offload will be disabled on this port.
-fn get_default_netbricks_config() -> NetbricksConfiguration {
+/// thread on core 0, and one scheduler on each core listed in `config.cores`.
+/// Packet buffers will be allocated from a 2 GB memory pool, with 64 MB cached
+/// per core. DPDK will be initialized as a primary process without any
+/// additional arguments. A single network interface/port is made available
+/// with one transmit and one receive queue per configured core and 256
+/// transmit/receive descriptors each. Loopback, hardware transmit segmentation
+/// offload, and hardware checksum offload will be disabled on this port.
+fn get_default_netbricks_config(config: &config::ClientConfig) -> NetbricksConfiguration {
     // General arguments supplied to netbricks.
     let net_config_name = String::from("client");
     let dpdk_secondary: bool = false;
     let net_primary_core: i32 = 0;
-    let net_cores: Vec<i32> = vec![1];
+    // One scheduler per configured core; one tx/rx queue is provisioned per
+    // core below so that each generator drives its own queue.
+    let net_cores: Vec<i32> = config.cores.clone();
     let net_strict_cores: bool = true;
     let net_pool_size: u32 = 2048 - 1;
     let net_cache_size: u32 = 64;
@@ -386,8 +996,8 @@ fn get_default_netbricks_config() -> NetbricksConfiguration {
 ///
 /// Returns a Netbricks context which can be used to setup and start the
 /// server/client.
-fn config_and_init_netbricks() -> NetbricksContext {
-    let net_config: NetbricksConfiguration = get_default_netbricks_config();
+fn config_and_init_netbricks(config: &config::ClientConfig) -> NetbricksContext {
+    let net_config: NetbricksConfiguration = get_default_netbricks_config(config);
 
     // Initialize Netbricks and return a handle.
     match initialize_system(&net_config) {
@@ -410,12 +1020,25 @@ fn main() {
     info!("Starting up Sandstorm client with config {:?}", config);
 
     // Setup Netbricks.
-    let mut net_context: NetbricksContext = config_and_init_netbricks();
+    let mut net_context: NetbricksContext = config_and_init_netbricks(&config);
+
+    // Statistics shared by every per-core generator, plus a counter handing out
+    // a distinct shard id to each scheduler as it is set up.
+    let stats = Arc::new(ClientStats::new(config.cores.len()));
+    let next_shard = Arc::new(AtomicUsize::new(0));
 
     // Setup the client pipeline.
     net_context.start_schedulers();
     net_context.add_pipeline_to_run(Arc::new(
-        move |ports, scheduler: &mut StandaloneScheduler| setup_client(&config, ports, scheduler),
+        move |ports, scheduler: &mut StandaloneScheduler| {
+            setup_client(
+                &config,
+                ports,
+                scheduler,
+                Arc::clone(&stats),
+                Arc::clone(&next_shard),
+            )
+        },
     ));
 
     // Run the client.
@@ -0,0 +1,223 @@
+/* Copyright (c) 2018 University of Utah
+ *
+ * Permission to use, copy, modify, and distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR(S) DISCLAIM ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL AUTHORS BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ */
+
+use std::cell::Cell;
+
+// FNV-1a 64-bit constants, used to scramble the chosen record index so that a
+// skewed selection does not map to a contiguous run of keys.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// The distribution from which record keys are drawn.
+///
+/// `Uniform` draws every record with equal probability; `Zipfian` draws a
+/// small hot set far more often, matching the YCSB default workloads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Distribution {
+    Uniform,
+    Zipfian,
+}
+
+impl Distribution {
+    /// Parses a distribution from its YCSB name ("uniform" or "zipfian").
+    /// Defaults to `Zipfian` for any unrecognized value.
+    pub fn parse(name: &str) -> Distribution {
+        match name {
+            "uniform" => Distribution::Uniform,
+            _ => Distribution::Zipfian,
+        }
+    }
+}
+
+/// Generates record keys over `n` records following either a uniform or a
+/// Zipfian distribution, scrambling the chosen index through an FNV hash so
+/// that the hot set is spread across the keyspace.
+pub struct KeyGenerator {
+    // Number of records in the keyspace.
+    n: usize,
+
+    // The distribution from which indices are drawn.
+    dist: Distribution,
+
+    // Precomputed normalization constant, sum_{i=1..n} 1 / i^theta.
+    zetan: f64,
+
+    // Precomputed exponent 1 / (1 - theta).
+    alpha: f64,
+
+    // Precomputed eta term of the Gray et al. quick Zipfian generator.
+    eta: f64,
+
+    // zeta(2, theta) = 1 + 0.5^theta, the first two terms of the sum.
+    zeta2theta: f64,
+
+    // State of the xorshift generator producing uniform variates.
+    rng: Cell<u64>,
+}
+
+impl KeyGenerator {
+    /// Constructs a key generator over `n` records. `theta` is the Zipfian skew
+    /// (0.99 is the YCSB default) and is ignored for the uniform distribution.
+    pub fn new(n: usize, dist: Distribution, theta: f64, seed: u64) -> KeyGenerator {
+        let zeta2theta = 1.0 + 0.5f64.powf(theta);
+        let zetan = KeyGenerator::zeta(n, theta);
+        let alpha = 1.0 / (1.0 - theta);
+        let eta = (1.0 - (2.0 / n as f64).powf(1.0 - theta)) / (1.0 - zeta2theta / zetan);
+
+        KeyGenerator {
+            n: n,
+            dist: dist,
+            zetan: zetan,
+            alpha: alpha,
+            eta: eta,
+            zeta2theta: zeta2theta,
+            rng: Cell::new(seed | 1),
+        }
+    }
+
+    /// Computes the normalization constant sum_{i=1..n} 1 / i^theta.
+    fn zeta(n: usize, theta: f64) -> f64 {
+        let mut sum = 0.0;
+        for i in 1..(n + 1) {
+            sum += 1.0 / (i as f64).powf(theta);
+        }
+        sum
+    }
+
+    /// Draws the next uniform variate in [0, 1) from the xorshift generator.
+    #[inline]
+    fn uniform(&self) -> f64 {
+        let mut x = self.rng.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng.set(x);
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Draws the next record index in [0, n), applying the FNV scramble so that
+    /// consecutive hot indices are scattered across the keyspace.
+    #[inline]
+    pub fn next_key(&self) -> u64 {
+        let index = match self.dist {
+            Distribution::Uniform => (self.uniform() * self.n as f64) as u64,
+            Distribution::Zipfian => self.next_zipfian(),
+        };
+        KeyGenerator::fnv(index) % self.n as u64
+    }
+
+    /// Draws a raw Zipfian-distributed index using the quick generator of Gray
+    /// et al.: the hottest two items are special-cased, the rest fall out of the
+    /// inverse transform via `eta` and `alpha`.
+    #[inline]
+    fn next_zipfian(&self) -> u64 {
+        let u = self.uniform();
+        let uz = u * self.zetan;
+
+        if uz < 1.0 {
+            0
+        } else if uz < self.zeta2theta {
+            // self.zeta2theta == 1 + 0.5^theta, the second item's cutoff.
+            1
+        } else {
+            (self.n as f64 * (self.eta * u - self.eta + 1.0).powf(self.alpha)) as u64
+        }
+    }
+
+    /// FNV-1a hash of `value`, used to scramble the chosen index.
+    #[inline]
+    fn fnv(value: u64) -> u64 {
+        let mut hash = FNV_OFFSET_BASIS;
+        for &byte in value.to_le_bytes().iter() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+        hash
+    }
+}
+
+/// A YCSB workload mix: the fraction of operations that are reads versus
+/// updates. The record count is not part of the mix; it is taken from
+/// `ClientConfig` (`n_keys`) by the key generator. No value size is
+/// configured: the requests built from this mix carry no value payload.
+#[derive(Clone, Copy, Debug)]
+pub struct Workload {
+    // Percentage (0..=100) of operations issued as reads. The remainder are
+    // the workload's writes.
+    pub read_pct: usize,
+}
+
+impl Workload {
+    /// Returns the read/write mix for one of the standard YCSB workloads.
+    /// A: 50/50, B: 95/5, C: 100/0 (read-only), F: read-modify-write (modeled
+    /// here as a 50/50 read/write mix like A).
+    pub fn from_name(name: &str) -> Workload {
+        let read_pct = match name {
+            "a" | "A" => 50,
+            "b" | "B" => 95,
+            "c" | "C" => 100,
+            "f" | "F" => 50,
+            _ => 100,
+        };
+        Workload { read_pct: read_pct }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Distribution, KeyGenerator};
+
+    // Every drawn key must land inside the keyspace for both distributions.
+    #[test]
+    fn keys_in_range() {
+        let n = 1000;
+        for &dist in &[Distribution::Uniform, Distribution::Zipfian] {
+            let gen = KeyGenerator::new(n, dist, 0.99, 0x1234_5678);
+            for _ in 0..100_000 {
+                assert!((gen.next_key() as usize) < n);
+            }
+        }
+    }
+
+    // A Zipfian generator must be far more skewed than a uniform one: its
+    // single hottest key should claim a much larger share of the draws than the
+    // 1/n a uniform generator would give.
+    #[test]
+    fn zipfian_is_skewed() {
+        let n = 1000;
+        let draws = 200_000;
+
+        let hottest = |dist| {
+            let gen = KeyGenerator::new(n, dist, 0.99, 0xdead_beef);
+            let mut counts = vec![0u64; n];
+            for _ in 0..draws {
+                counts[gen.next_key() as usize] += 1;
+            }
+            *counts.iter().max().unwrap()
+        };
+
+        let zipf_top = hottest(Distribution::Zipfian);
+        let uniform_top = hottest(Distribution::Uniform);
+
+        // The Zipfian hot key should dominate; uniform's busiest key stays near
+        // draws/n. A 5x margin is a conservative lower bound for theta = 0.99.
+        assert!(
+            zipf_top > uniform_top * 5,
+            "zipf_top={} uniform_top={}",
+            zipf_top,
+            uniform_top
+        );
+    }
+}
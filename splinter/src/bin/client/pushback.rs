@@ -32,6 +32,7 @@ use std::mem;
 use std::mem::transmute;
 use std::sync::Arc;
 
+use spin::Once;
 use spin::RwLock;
 
 use db::config;
@@ -61,6 +62,232 @@ const NUM_MUL: u64 = 1000;
 // Flag to indicate that the client has finished sending and receiving the packets.
 static mut FINISHED: bool = false;
 
+// Finalized per-core latency histograms, keyed by core id. Each pipeline inserts
+// its own histogram once, on teardown; `main()` merges them after `FINISHED` so
+// the reported tail reflects all cores rather than just the master's traffic.
+static CORE_HISTOGRAMS: Once<RwLock<HashMap<i32, Histogram>>> = Once::new();
+
+// Returns the process-wide per-core histogram map, initializing it on first use.
+fn core_histograms() -> &'static RwLock<HashMap<i32, Histogram>> {
+    CORE_HISTOGRAMS.call_once(|| RwLock::new(HashMap::new()))
+}
+
+// Bits of sub-bucket resolution kept by `Histogram`. Seven bits (128
+// sub-buckets per exponent) hold every sample to under 1/64 (~1.6%) relative
+// error.
+const HIST_SIG_FIGS: u32 = 7;
+
+/// A streaming, fixed-memory latency histogram in the style of HDRHistogram.
+///
+/// Each value lands in an exponent bucket (the position of its highest set bit)
+/// and an equal-width sub-bucket within it, giving a constant relative error.
+/// `record()` is O(1) and memory is bounded regardless of the number of
+/// samples, so long runs no longer accumulate an unbounded `Vec<u64>`.
+#[derive(Clone)]
+struct Histogram {
+    // Flat counts indexed by `bucket * sub_bucket_count + sub_bucket`.
+    counts: Vec<u64>,
+
+    // Sub-buckets per exponent (a power of two).
+    sub_bucket_count: u64,
+
+    // Total number of samples recorded so far.
+    total: u64,
+
+    // Running sum of recorded values, for the mean.
+    sum: u64,
+
+    // Smallest and largest values seen so far. While empty `min` holds
+    // `u64::max_value()` and `max` holds 0; the accessors report 0 for both.
+    min: u64,
+    max: u64,
+}
+
+impl Histogram {
+    /// Returns an empty histogram sized to cover any 64-bit cycle count.
+    fn new() -> Histogram {
+        let sub_bucket_count = 1u64 << HIST_SIG_FIGS;
+        Histogram {
+            counts: vec![0; (64 * sub_bucket_count) as usize],
+            sub_bucket_count: sub_bucket_count,
+            total: 0,
+            sum: 0,
+            min: u64::max_value(),
+            max: 0,
+        }
+    }
+
+    /// Records a single value in O(1).
+    #[inline]
+    fn record(&mut self, value: u64) {
+        let bucket = (64u32.saturating_sub(value.leading_zeros())).saturating_sub(HIST_SIG_FIGS)
+            as u64;
+        let sub = (value >> bucket) & (self.sub_bucket_count - 1);
+        self.counts[(bucket * self.sub_bucket_count + sub) as usize] += 1;
+        self.total += 1;
+        self.sum += value;
+        if value < self.min {
+            self.min = value;
+        }
+        if value > self.max {
+            self.max = value;
+        }
+    }
+
+    /// Returns the value at percentile `q` (0.0..=1.0) by walking cumulative
+    /// counts until the target rank is crossed.
+    fn percentile(&self, q: f64) -> u64 {
+        if self.total == 0 {
+            return 0;
+        }
+        let target = (q * self.total as f64) as u64;
+        let mut cumulative = 0u64;
+        for (idx, &count) in self.counts.iter().enumerate() {
+            cumulative += count;
+            if cumulative >= target {
+                let bucket = idx as u64 / self.sub_bucket_count;
+                let sub = idx as u64 % self.sub_bucket_count;
+                return sub << bucket;
+            }
+        }
+        0
+    }
+
+    /// Folds another histogram into this one by adding its bucket counts and
+    /// combining the scalar aggregates. Both must share the same bucket layout,
+    /// which holds for any two `Histogram::new()` instances.
+    fn merge(&mut self, other: &Histogram) {
+        for (dst, src) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *dst += *src;
+        }
+        self.total += other.total;
+        self.sum += other.sum;
+        if other.total > 0 {
+            if other.min < self.min {
+                self.min = other.min;
+            }
+            if other.max > self.max {
+                self.max = other.max;
+            }
+        }
+    }
+
+    /// Smallest recorded value, or 0 if the histogram is empty.
+    fn min(&self) -> u64 {
+        if self.total == 0 {
+            0
+        } else {
+            self.min
+        }
+    }
+
+    /// Largest recorded value, or 0 if the histogram is empty.
+    fn max(&self) -> u64 {
+        self.max
+    }
+
+    /// Arithmetic mean of the recorded values, or 0 if the histogram is empty.
+    fn mean(&self) -> u64 {
+        if self.total == 0 {
+            0
+        } else {
+            self.sum / self.total
+        }
+    }
+}
+
+// Number of tenant priority classes (high, normal, low) for pushed-back tasks.
+const NUM_CLASSES: usize = 3;
+
+// Per-class deficit-round-robin quantum, in cycles. Higher-priority classes are
+// granted more credit per round and therefore more CPU share.
+const CLASS_QUANTUM: [u64; NUM_CLASSES] = [30_000, 20_000, 10_000];
+
+/// A deficit-round-robin run-queue of pushed-back tasks partitioned into tenant
+/// priority classes. Each class accumulates a quantum of credits per round; a
+/// class may run only while it has credit left, and a task's measured cycle
+/// cost is charged back against its class. This keeps a heavy tenant's
+/// extensions from starving latency-sensitive ones the way a single FIFO did.
+struct RunQueue {
+    // Pending tasks per class, each tagged with its owning tenant id.
+    classes: [VecDeque<(u32, TaskManager)>; NUM_CLASSES],
+
+    // Remaining credit per class, in cycles.
+    deficit: [i64; NUM_CLASSES],
+
+    // Round-robin cursor over the classes.
+    cursor: usize,
+}
+
+impl RunQueue {
+    fn new() -> RunQueue {
+        RunQueue {
+            classes: [VecDeque::new(), VecDeque::new(), VecDeque::new()],
+            deficit: [0; NUM_CLASSES],
+            cursor: 0,
+        }
+    }
+
+    /// Maps a tenant id to its priority class (0 = high .. NUM_CLASSES-1 = low).
+    ///
+    /// Priority is derived from tenant identity rather than scattered by a
+    /// modulo: the lowest-numbered tenants are the highest priority and each
+    /// subsequent tenant drops a class until the lowest is reached. Tenant id
+    /// thus acts as an explicit priority rank, so the per-class quanta in
+    /// `CLASS_QUANTUM` actually weight CPU share by tenant priority.
+    #[inline]
+    fn class_of(tenant: u32) -> usize {
+        let rank = tenant.saturating_sub(1) as usize;
+        rank.min(NUM_CLASSES - 1)
+    }
+
+    /// Enqueues `manager` into the class owned by `tenant`.
+    fn push(&mut self, tenant: u32, manager: TaskManager) {
+        let class = RunQueue::class_of(tenant);
+        self.classes[class].push_back((tenant, manager));
+    }
+
+    /// Selects the next class to run: the first non-empty class, in round-robin
+    /// order, that still has (or, after topping up its quantum, gains) credit.
+    fn select(&mut self) -> Option<usize> {
+        for _ in 0..NUM_CLASSES {
+            let class = self.cursor;
+            self.cursor = (self.cursor + 1) % NUM_CLASSES;
+
+            if self.classes[class].is_empty() {
+                // A drained class forfeits its leftover credit: deficit-round-
+                // robin zeroes the deficit when a queue empties so that a class
+                // which later refills cannot cash in unearned backlog as an
+                // immediate burst.
+                self.deficit[class] = 0;
+                continue;
+            }
+
+            // Top the class up by its quantum when it is visited with a backlog.
+            self.deficit[class] += CLASS_QUANTUM[class] as i64;
+            if self.deficit[class] > 0 {
+                return Some(class);
+            }
+        }
+        None
+    }
+
+    /// Pops the front task of `class`.
+    fn pop(&mut self, class: usize) -> Option<(u32, TaskManager)> {
+        self.classes[class].pop_front()
+    }
+
+    /// Pushes a yielded task back onto the tail of its class.
+    fn requeue(&mut self, tenant: u32, manager: TaskManager) {
+        self.classes[RunQueue::class_of(tenant)].push_back((tenant, manager));
+    }
+
+    /// Charges `cost` cycles against `class`'s remaining credit.
+    fn charge(&mut self, class: usize, cost: u64) {
+        self.deficit[class] -= cost as i64;
+    }
+}
+
 // PUSHBACK benchmark.
 // The benchmark is created and parameterized with `new()`. Many threads
 // share the same benchmark instance. Each thread can call `abc()` which
@@ -157,6 +384,24 @@ impl Pushback {
     }
 }
 
+/// A snapshot of an outstanding invoke() request, retained so that it can be
+/// re-issued as a hedged duplicate if it runs long. Keyed in `inflight` by the
+/// request's stamp (its send timestamp / id).
+struct Hedge {
+    // Time stamp at which the original copy was sent.
+    sent: u64,
+
+    // Tenant and extension-name length needed to re-issue the request.
+    tenant: u32,
+    name_length: u32,
+
+    // Exact payload of the original invoke(), replayed verbatim by the hedge.
+    payload: Vec<u8>,
+
+    // True once a duplicate copy has been issued for this request.
+    hedged: bool,
+}
+
 /// Receives responses to PUSHBACK requests sent out by PushbackSend.
 struct PushbackRecvSend<T>
 where
@@ -175,13 +420,17 @@ where
     // The total number of responses received so far.
     recvd: u64,
 
-    // Vector of sampled request latencies. Required to calculate distributions once all responses
-    // have been received.
-    latencies: Vec<u64>,
+    // Streaming histogram of sampled request latencies. Bounded memory, and
+    // queryable for arbitrary percentiles both mid-run and at teardown.
+    latencies: Histogram,
 
     // If true, this receiver will make latency measurements.
     master: bool,
 
+    // Core id this pipeline runs on. Used to key its finalized histogram in the
+    // process-wide map so all cores can be merged at shutdown.
+    core: i32,
+
     // Time stamp in cycles at which measurement stopped.
     stop: u64,
 
@@ -198,9 +447,109 @@ where
     sent: u64,
 
     // If true, RPC requests corresponding to native get() and put() operations are sent out. If
-    // false, invoke() based RPC requests are sent out.
+    // false, invoke() based RPC requests are sent out. With the adaptive ratio
+    // controller below this only seeds the initial value of `invoke_p`; the
+    // actual per-request choice is a coin flip against `invoke_p`.
     native: bool,
 
+    // Fraction of requests (in [0, 1]) issued as server invoke() RPCs; the rest
+    // are executed on the client after a native get(). Tuned online by the
+    // hill-climbing controller in `adapt()`.
+    invoke_p: f64,
+
+    // The value of `invoke_p` at the centre of the current perturbation pair.
+    p_base: f64,
+
+    // Hill-climb phase: 0 probes at `p_base + delta`, 1 probes at
+    // `p_base - delta`. After both probes the gradient is estimated and
+    // `p_base` stepped.
+    adapt_phase: u8,
+
+    // Throughput (ops/sec) measured during the `p_base + delta` probe.
+    t_plus: f64,
+
+    // Perturbation magnitude applied to `invoke_p` each probe epoch.
+    adapt_delta: f64,
+
+    // Tail-latency SLO in cycles. When the measured p99 breaches it, the
+    // controller sheds server load by forcing `invoke_p` down.
+    slo: u64,
+
+    // Number of responses that make up one adaptation epoch.
+    epoch_len: u64,
+
+    // Response count and cycle stamp at which the current epoch began.
+    epoch_start_recvd: u64,
+    epoch_start: u64,
+
+    // State of the xorshift generator backing the per-request invoke/native coin.
+    coin: u64,
+
+    // If true, requests are paced by an open-loop Poisson arrival process at
+    // `target_rate`, independently of the CUBIC window (the `max_outstanding`
+    // cap still applies); if false, the closed-loop CUBIC window governs sending.
+    open_loop: bool,
+
+    // Target offered load in requests per second for open-loop mode.
+    target_rate: f64,
+
+    // When true, requests outstanding longer than the running p95 are re-issued
+    // once as a hedged duplicate to cut the tail; whichever copy returns first
+    // wins and the straggler is ignored by request id.
+    hedge: bool,
+
+    // Snapshots of outstanding invoke() requests eligible for hedging, keyed by
+    // request id. Only populated when `hedge` is enabled.
+    inflight: RefCell<HashMap<u64, Hedge>>,
+
+    // One sender per server UDP port other than port 0, which original copies
+    // use. A hedged duplicate is re-issued through one of these ports
+    // (round-robined by `hedge_rr`), so the two copies race across distinct
+    // server endpoints rather than piling onto the same one. Empty unless
+    // `hedge` is enabled and there are at least two server ports.
+    hedge_senders: Vec<Arc<dispatch::Sender>>,
+
+    // Round-robin cursor over `hedge_senders` for the next hedged copy.
+    hedge_rr: usize,
+
+    // Count of hedged (duplicate) requests issued, and of completed requests
+    // for which a hedge copy had been issued when the first response arrived.
+    // Both copies carry the same stamp, so the second count does not tell
+    // which copy won; it is an upper bound on responses served by the hedge.
+    hedge_fired: u64,
+    hedge_served: u64,
+
+    // Hard cap on the number of in-flight requests. `send()` stops transmitting
+    // once `outstanding` reaches this window and `recv()` frees a slot as each
+    // response arrives, mirroring the request-buffer semaphore of a mature RPC
+    // client and preventing the client from over-driving the server.
+    max_outstanding: u64,
+
+    // When true (open-loop only), each request is stamped with its scheduled
+    // dispatch time rather than its actual transmit time, so the measured
+    // latency includes the time the request spent waiting in the local backlog.
+    // This is coordinated-omission correction: a stalled server's queueing is
+    // attributed to every delayed request instead of being hidden.
+    coordinated_omission: bool,
+
+    // Cycle count at which the next open-loop request is scheduled to dispatch.
+    next_send_tsc: u64,
+
+    // Last request stamp actually issued. Stamps double as the key for the
+    // `manager`, `native_state`, and `inflight` maps as well as the value the
+    // server echoes back, so they must be strictly increasing: sub-cycle Poisson
+    // gaps (and back-to-back `rdtsc()` reads) can otherwise repeat a value and
+    // collide in those maps, losing a response. `emit_one` bumps each stamp past
+    // this watermark to guarantee uniqueness.
+    last_stamp: u64,
+
+    // Cycles per second, cached for converting inter-arrival gaps to cycles.
+    cycles_per_second: f64,
+
+    // Histogram of per-request queueing delay (actual send time minus scheduled
+    // dispatch time). Growing queueing delay exposes offered-vs-achieved load.
+    queueing: Histogram,
+
     // Payload for an invoke() based get operation. Required in order to avoid making intermediate
     // copies of the extension name, table id, and key.
     payload_get: RefCell<Vec<u8>>,
@@ -212,10 +561,44 @@ where
     // Flag to indicate if the procedure is finished or not.
     finished: bool,
 
-    // To keep the mapping between sent and received packets. The client doesn't want to send
-    // more than 32(XXX) outstanding packets.
+    // Set once this core has published its histogram into `CORE_HISTOGRAMS`, so
+    // that teardown never publishes (or overwrites) it twice.
+    published: bool,
+
+    // To keep the mapping between sent and received packets. The number of
+    // outstanding packets is bounded by the adaptive CUBIC window below in
+    // closed loop, and by `max_outstanding` in both modes.
     outstanding: u64,
 
+    // Current CUBIC congestion window, i.e. the target number of outstanding
+    // requests. `send()` transmits while `outstanding < cubic_w.floor()`.
+    cubic_w: f64,
+
+    // Window size at the last congestion event. The cubic term ramps `cubic_w`
+    // back towards this value after a backoff.
+    cubic_wmax: f64,
+
+    // Time stamp (in cycles) at which the current congestion epoch started.
+    cubic_epoch: u64,
+
+    // CUBIC scaling constant `C` (typically 0.4).
+    cubic_c: f64,
+
+    // CUBIC multiplicative-decrease factor `beta` (typically 0.7).
+    cubic_beta: f64,
+
+    // A congestion signal fires when the rolling p99 of recent latencies exceeds
+    // `tail_factor` times the learned baseline latency.
+    tail_factor: f64,
+
+    // Learned baseline latency in cycles, an EWMA of observed samples. Used as
+    // the reference point for the tail-latency congestion signal.
+    latency_baseline: f64,
+
+    // A bounded window of recent latencies (in cycles) used to estimate the
+    // rolling p99 for the congestion signal without scanning the full history.
+    recent: VecDeque<u64>,
+
     /// A ref counted pointer to a master service. The master service
     /// implements the primary interface to the database.
     master_service: Arc<Master>,
@@ -225,8 +608,9 @@ where
     manager: RefCell<HashMap<u64, TaskManager>>,
 
     // Run-queue of tasks waiting to execute. Tasks on this queue have either yielded, or have been
-    // recently enqueued and never run before.
-    waiting: RwLock<VecDeque<TaskManager>>,
+    // recently enqueued and never run before. Partitioned into tenant priority
+    // classes and scheduled with deficit round-robin so no tenant starves.
+    waiting: RwLock<RunQueue>,
 
     // Number of tasks completed on the client, after server pushback. Wraps around
     // after each 1L such tasks.
@@ -236,6 +620,11 @@ where
     // extensions on its end.
     cycle_counter: CycleCounter,
 
+    // Per-tenant cycle accounting for pushed-back task execution, keyed by
+    // tenant id. Reported alongside `cycle_counter` so the benchmark can
+    // characterize fairness, not just an aggregate average.
+    tenant_cycles: RefCell<HashMap<u32, u64>>,
+
     // Keeps track of the state of a multi-operation request. For example, an extension performs
     // four get operations before performing aggregation and all these get operations are dependent
     // on the previous value.
@@ -264,6 +653,7 @@ where
         rx_port: T,
         resps: u64,
         master: bool,
+        core: i32,
         config: &config::ClientConfig,
         tx_port: CacheAligned<PortQueue>,
         reqs: u64,
@@ -293,13 +683,29 @@ where
             transmute::<u16, [u8; 2]>((config.key_len as u16).to_le())
         });
         payload_put.resize(payload_len, 0);
+
+        // Original requests always leave on port 0 (see `sender` below). When
+        // hedging is on, build one sender for every *other* server UDP port
+        // (`1..dst_ports`) so a duplicate copy is guaranteed to target a port
+        // distinct from the one its original used. The pool is therefore empty
+        // whenever there are fewer than two ports, which is exactly when no
+        // distinct port exists to hedge onto.
+        let hedge_senders: Vec<Arc<dispatch::Sender>> = if config.hedge {
+            (1..dst_ports)
+                .map(|port| Arc::new(dispatch::Sender::new(config, tx_port.clone(), port)))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
         PushbackRecvSend {
             receiver: dispatch::Receiver::new(rx_port),
             responses: resps,
             start: cycles::rdtsc(),
             recvd: 0,
-            latencies: Vec::with_capacity(resps as usize),
+            latencies: Histogram::new(),
             master: master,
+            core: core,
             stop: 0,
             workload: RefCell::new(Pushback::new(
                 config.key_len,
@@ -310,19 +716,55 @@ where
                 config.num_tenants,
                 config.tenant_skew,
             )),
-            sender: Arc::new(dispatch::Sender::new(config, tx_port, dst_ports)),
+            sender: Arc::new(dispatch::Sender::new(config, tx_port, 0)),
             requests: reqs,
             sent: 0,
             native: !config.use_invoke,
+            // Seed the ratio from the static flag: all-invoke or all-native.
+            invoke_p: if config.use_invoke { 1.0 } else { 0.0 },
+            p_base: if config.use_invoke { 1.0 } else { 0.0 },
+            adapt_phase: 0,
+            t_plus: 0.0,
+            adapt_delta: config.adapt_delta,
+            slo: (config.slo_us as f64 * 1e-6 * cycles::cycles_per_second() as f64) as u64,
+            epoch_len: config.adapt_epoch,
+            epoch_start_recvd: 0,
+            epoch_start: cycles::rdtsc(),
+            coin: 0x9e37_79b9_7f4a_7c15 ^ cycles::rdtsc(),
+            open_loop: config.open_loop,
+            target_rate: config.target_rate,
+            coordinated_omission: config.coordinated_omission,
+            hedge: config.hedge,
+            inflight: RefCell::new(HashMap::new()),
+            hedge_senders: hedge_senders,
+            hedge_rr: 0,
+            hedge_fired: 0,
+            hedge_served: 0,
+            max_outstanding: config.max_outstanding,
+            // Schedule the first open-loop arrival at construction time.
+            next_send_tsc: cycles::rdtsc(),
+            last_stamp: 0,
+            cycles_per_second: cycles::cycles_per_second() as f64,
+            queueing: Histogram::new(),
             payload_get: RefCell::new(payload_get),
             payload_put: RefCell::new(payload_put),
             finished: false,
+            published: false,
             outstanding: 0,
+            cubic_w: config.cubic_w_init as f64,
+            cubic_wmax: config.cubic_w_init as f64,
+            cubic_epoch: cycles::rdtsc(),
+            cubic_c: config.cubic_c,
+            cubic_beta: config.cubic_beta,
+            tail_factor: config.tail_factor,
+            latency_baseline: 0.0,
+            recent: VecDeque::with_capacity(128),
             master_service: Arc::clone(&masterservice),
             manager: RefCell::new(HashMap::new()),
-            waiting: RwLock::new(VecDeque::new()),
+            waiting: RwLock::new(RunQueue::new()),
             pushback_completed: 0,
             cycle_counter: CycleCounter::new(),
+            tenant_cycles: RefCell::new(HashMap::new()),
             native_state: RefCell::new(HashMap::with_capacity(32)),
         }
     }
@@ -356,57 +798,310 @@ where
         return mul;
     }
 
+    /// Records a fresh latency sample into the rolling window used by the CUBIC
+    /// congestion signal and folds it into the learned baseline.
+    fn note_latency(&mut self, latency: u64) {
+        if self.recent.len() == 128 {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(latency);
+
+        // EWMA of observed latency; the baseline tracks the "good" latency the
+        // window is allowed to ramp back up to.
+        if self.latency_baseline == 0.0 {
+            self.latency_baseline = latency as f64;
+        } else {
+            self.latency_baseline = 0.99 * self.latency_baseline + 0.01 * latency as f64;
+        }
+    }
+
+    /// Returns true when the rolling p99 of recent latencies exceeds
+    /// `tail_factor` times the learned baseline, i.e. the server is saturating.
+    fn tail_breached(&self) -> bool {
+        if self.recent.len() < 16 || self.latency_baseline == 0.0 {
+            return false;
+        }
+        let mut sorted: Vec<u64> = self.recent.iter().cloned().collect();
+        sorted.sort();
+        let p99 = sorted[(sorted.len() * 99) / 100];
+        p99 as f64 > self.tail_factor * self.latency_baseline
+    }
+
+    /// Computes the current CUBIC window, backing off on a tail-latency breach
+    /// and otherwise letting the cubic term ramp the window back towards
+    /// `cubic_wmax`.
+    fn cubic_window(&mut self) -> f64 {
+        let now = cycles::rdtsc();
+
+        if self.tail_breached() {
+            // Multiplicative decrease: remember the window, shrink it, and start
+            // a fresh epoch. On the next call without a breach the cubic term
+            // restarts at `cubic_wmax * (1 - cubic_beta)` and ramps back
+            // towards `cubic_wmax`.
+            self.cubic_wmax = self.cubic_w;
+            self.cubic_w = (self.cubic_w * self.cubic_beta).max(1.0);
+            self.cubic_epoch = now;
+            return self.cubic_w;
+        }
+
+        let t = cycles::to_seconds(now - self.cubic_epoch);
+        let k = (self.cubic_wmax * self.cubic_beta / self.cubic_c).cbrt();
+        self.cubic_w = (self.cubic_c * (t - k).powi(3) + self.cubic_wmax).max(1.0);
+        self.cubic_w
+    }
+
+    /// Draws the next coin in [0, 1) for the per-request invoke/native choice.
+    #[inline]
+    fn next_coin(&mut self) -> f64 {
+        let mut x = self.coin;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.coin = x;
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Runs one step of the hill-climbing controller that tunes `invoke_p`.
+    ///
+    /// Each call that closes an epoch measures throughput and the current p99.
+    /// Two consecutive epochs probe `p_base ± delta`; their throughputs give a
+    /// local gradient, and `p_base` is stepped in the throughput-increasing
+    /// direction. A p99 above the SLO overrides the gradient and forces
+    /// `invoke_p` down to shed server load.
+    fn adapt(&mut self) {
+        if self.epoch_len == 0 || self.recvd - self.epoch_start_recvd < self.epoch_len {
+            return;
+        }
+
+        let now = cycles::rdtsc();
+        let ops = (self.recvd - self.epoch_start_recvd) as f64;
+        let throughput = ops / cycles::to_seconds(now - self.epoch_start);
+        let p99 = self.latencies.percentile(0.99);
+
+        // Start the next epoch's measurement window.
+        self.epoch_start = now;
+        self.epoch_start_recvd = self.recvd;
+
+        // A breach of the tail SLO always sheds server load, regardless of the
+        // gradient search.
+        if self.slo > 0 && p99 > self.slo {
+            self.p_base = (self.p_base - self.adapt_delta).max(0.0);
+            self.invoke_p = self.p_base;
+            self.adapt_phase = 0;
+            return;
+        }
+
+        match self.adapt_phase {
+            0 => {
+                // Record the throughput of the +delta probe and move to -delta.
+                self.t_plus = throughput;
+                self.invoke_p = (self.p_base - self.adapt_delta).max(0.0);
+                self.adapt_phase = 1;
+            }
+
+            _ => {
+                // Estimate the gradient and step p_base towards higher throughput.
+                let t_minus = throughput;
+                if self.t_plus >= t_minus {
+                    self.p_base = (self.p_base + self.adapt_delta).min(1.0);
+                } else {
+                    self.p_base = (self.p_base - self.adapt_delta).max(0.0);
+                }
+                self.invoke_p = (self.p_base + self.adapt_delta).min(1.0);
+                self.adapt_phase = 0;
+            }
+        }
+    }
+
+    /// Issues a single request, timestamped `curr`, choosing the native or
+    /// invoke() tier via a coin flip against the adaptive ratio. Bumps the
+    /// in-flight and sent counters but does not itself decide when to send.
+    fn emit_one(&mut self, curr: u64) {
+        // Stamps key the outstanding-request maps and are echoed by the server,
+        // so force a strictly increasing value: a repeated stamp (sub-cycle
+        // Poisson gap, or two `rdtsc()` reads in the same tick) would otherwise
+        // overwrite a live entry and strand its response.
+        let curr = if curr > self.last_stamp {
+            curr
+        } else {
+            self.last_stamp + 1
+        };
+        self.last_stamp = curr;
+
+        // Choose this request's tier: a coin flip against the adaptive ratio
+        // decides whether it is executed on the client (native) or pushed to
+        // the server as an invoke() RPC.
+        let as_native = self.next_coin() >= self.invoke_p;
+
+        if as_native {
+            // Configured to issue native RPCs, issue a regular get()/put() operation.
+            self.workload.borrow_mut().abc(
+                |tenant, key| self.sender.send_get(tenant, 1, key, curr),
+                |tenant, key, val| self.sender.send_put(tenant, 1, key, val, curr),
+            );
+            self.native_state.borrow_mut().entry(curr).or_insert(1);
+            self.outstanding += 1;
+        } else {
+            // Configured to issue invoke() RPCs.
+            let mut p_get = self.payload_get.borrow_mut();
+            let mut p_put = self.payload_put.borrow_mut();
+
+            // XXX Heavily dependent on how `Pushback` creates a key. Only the first four
+            // bytes of the key matter, the rest are zero. The value is always zero.
+            self.workload.borrow_mut().abc(
+                |tenant, key| {
+                    // First 16 bytes on the payload were already pre-populated with the
+                    // extension name (8 bytes), and the table id (8 bytes). Just write in the
+                    // first 4 bytes of the key.
+                    p_get[16..20].copy_from_slice(&key[0..4]);
+                    self.add_request(&p_get, tenant, 8, curr);
+                    if self.hedge {
+                        self.inflight.borrow_mut().insert(
+                            curr,
+                            Hedge {
+                                sent: curr,
+                                tenant: tenant,
+                                name_length: 8,
+                                payload: p_get[..].to_vec(),
+                                hedged: false,
+                            },
+                        );
+                    }
+                    self.sender.send_invoke(tenant, 8, &p_get, curr)
+                },
+                |tenant, key, _val| {
+                    // First 18 bytes on the payload were already pre-populated with the
+                    // extension name (8 bytes), the table id (8 bytes), and the key length (2
+                    // bytes). Just write in the first 4 bytes of the key. The value is anyway
+                    // always zero.
+                    p_put[18..22].copy_from_slice(&key[0..4]);
+                    self.add_request(&p_put, tenant, 8, curr);
+                    if self.hedge {
+                        self.inflight.borrow_mut().insert(
+                            curr,
+                            Hedge {
+                                sent: curr,
+                                tenant: tenant,
+                                name_length: 8,
+                                payload: p_put[..].to_vec(),
+                                hedged: false,
+                            },
+                        );
+                    }
+                    self.sender.send_invoke(tenant, 8, &p_put, curr)
+                },
+            );
+            self.outstanding += 1;
+        }
+
+        self.sent += 1;
+    }
+
+    /// Re-issues a duplicate copy of every outstanding invoke() request that has
+    /// been in flight longer than the running p95 latency. Each request is
+    /// hedged at most once; whichever copy the server answers first wins and the
+    /// straggler is discarded by id on the recv path.
+    fn hedge_stragglers(&mut self) {
+        // The tail threshold is the current p95 estimate; nothing to do until
+        // the histogram has enough samples to produce one.
+        let threshold = self.latencies.percentile(0.95);
+        if threshold == 0 {
+            return;
+        }
+
+        // `hedge_senders` holds only the non-primary ports, so it is empty
+        // exactly when there are fewer than two server ports; with no second
+        // port there is nowhere distinct to hedge onto, and no request is
+        // marked hedged.
+        if self.hedge_senders.is_empty() {
+            return;
+        }
+
+        let now = cycles::rdtsc();
+
+        // Collect the copies to re-issue under the map borrow, marking them
+        // hedged, then send them once the borrow is released.
+        let mut resend: Vec<(u32, u32, u64, Vec<u8>)> = Vec::new();
+        {
+            let mut inflight = self.inflight.borrow_mut();
+            for (id, req) in inflight.iter_mut() {
+                if !req.hedged && now.saturating_sub(req.sent) > threshold {
+                    req.hedged = true;
+                    resend.push((req.tenant, req.name_length, *id, req.payload.clone()));
+                }
+            }
+        }
+
+        for (tenant, name_length, id, payload) in resend.into_iter() {
+            // Re-issue the duplicate through the next non-primary port in round-
+            // robin order. Because port 0 (the original's port) is excluded from
+            // this pool, the copy always lands on a different port.
+            let sender = &self.hedge_senders[self.hedge_rr % self.hedge_senders.len()];
+            sender.send_invoke(tenant, name_length, &payload, id);
+            self.hedge_rr = self.hedge_rr.wrapping_add(1);
+            self.hedge_fired += 1;
+        }
+    }
+
+    /// Draws the next exponential inter-arrival gap, in cycles, for the
+    /// open-loop Poisson process: `-ln(U)/λ` seconds scaled to cycles.
+    #[inline]
+    fn next_gap(&mut self) -> u64 {
+        // Clamp U away from zero so the logarithm stays finite.
+        let u = self.next_coin().max(1e-12);
+        let gap_secs = -u.ln() / self.target_rate;
+        // Never return a zero gap: the scheduled time doubles as a request stamp
+        // and must advance so two arrivals cannot share a deadline.
+        ((gap_secs * self.cycles_per_second) as u64).max(1)
+    }
+
     fn send(&mut self) {
+        // Before generating new load, re-issue any straggling requests as
+        // hedged duplicates to cut the tail.
+        if self.hedge {
+            self.hedge_stragglers();
+        }
+
         // Return if there are no more requests to generate.
         if self.requests <= self.sent {
             return;
         }
 
-        while self.outstanding < 32 {
-            // Get the current time stamp so that we can determine if it is time to issue the next RPC.
-            let curr = cycles::rdtsc();
-
-            if self.native == true {
-                // Configured to issue native RPCs, issue a regular get()/put() operation.
-                self.workload.borrow_mut().abc(
-                    |tenant, key| self.sender.send_get(tenant, 1, key, curr),
-                    |tenant, key, val| self.sender.send_put(tenant, 1, key, val, curr),
-                );
-                self.native_state.borrow_mut().entry(curr).or_insert(1);
-                self.outstanding += 1;
-            } else {
-                // Configured to issue invoke() RPCs.
-                let mut p_get = self.payload_get.borrow_mut();
-                let mut p_put = self.payload_put.borrow_mut();
-
-                // XXX Heavily dependent on how `Pushback` creates a key. Only the first four
-                // bytes of the key matter, the rest are zero. The value is always zero.
-                self.workload.borrow_mut().abc(
-                    |tenant, key| {
-                        // First 16 bytes on the payload were already pre-populated with the
-                        // extension name (8 bytes), and the table id (8 bytes). Just write in the
-                        // first 4 bytes of the key.
-                        p_get[16..20].copy_from_slice(&key[0..4]);
-                        self.add_request(&p_get, tenant, 8, curr);
-                        self.sender.send_invoke(tenant, 8, &p_get, curr)
-                    },
-                    |tenant, key, _val| {
-                        // First 18 bytes on the payload were already pre-populated with the
-                        // extension name (8 bytes), the table id (8 bytes), and the key length (2
-                        // bytes). Just write in the first 4 bytes of the key. The value is anyway
-                        // always zero.
-                        p_put[18..22].copy_from_slice(&key[0..4]);
-                        self.add_request(&p_put, tenant, 8, curr);
-                        self.sender.send_invoke(tenant, 8, &p_put, curr)
-                    },
-                );
-                self.outstanding += 1;
+        if self.open_loop {
+            // Open-loop: dispatch every request whose scheduled arrival time has
+            // already passed, independently of the CUBIC window but never with
+            // more than `max_outstanding` requests in flight. Each
+            // request's queueing delay is the gap between when it was actually
+            // sent and when the Poisson process scheduled it.
+            let now = cycles::rdtsc();
+            while self.requests > self.sent
+                && self.outstanding < self.max_outstanding
+                && now >= self.next_send_tsc
+            {
+                let scheduled = self.next_send_tsc;
+                // Coordinated-omission correction: stamp the request with its
+                // intended (scheduled) send time so that latency, computed on
+                // the recv path as `recv_tsc - stamp`, absorbs the local
+                // queueing delay. Otherwise stamp with the actual transmit time.
+                let stamp = if self.coordinated_omission {
+                    scheduled
+                } else {
+                    now
+                };
+                self.emit_one(stamp);
+                self.queueing.record(now.saturating_sub(scheduled));
+                self.next_send_tsc = self.next_send_tsc.wrapping_add(self.next_gap());
             }
+            return;
+        }
 
-            // Update the time stamp at which the next request should be generated, assuming that
-            // the first request was sent out at self.start.
-            self.sent += 1;
-            //self.next = self.start + self.sent * self.rate_inv;
+        // Closed-loop: keep the pipe full up to the current CUBIC window, but
+        // never exceed the configured hard outstanding cap.
+        let window = (self.cubic_window().floor() as u64).min(self.max_outstanding);
+        while self.outstanding < window {
+            // Get the current time stamp so that we can determine if it is time to issue the next RPC.
+            let curr = cycles::rdtsc();
+            self.emit_one(curr);
         }
     }
 
@@ -421,98 +1116,113 @@ where
         // If there are packets, sample the latency of the server.
         if let Some(mut packets) = self.receiver.recv_res() {
             while let Some(packet) = packets.pop() {
-                if self.native == false {
-                    let curr = cycles::rdtsc();
-
-                    match parse_rpc_opcode(&packet) {
-                        // The response corresponds to an invoke() RPC.
-                        OpCode::SandstormInvokeRpc => {
-                            let p = packet.parse_header::<InvokeResponse>();
-                            match p.get_header().common_header.status {
-                                // If the status is StatusOk then add the stamp to the latencies and
-                                // free the packet.
-                                RpcStatus::StatusOk => {
-                                    self.recvd += 1;
-                                    self.latencies
-                                        .push(curr - p.get_header().common_header.stamp);
-                                    self.outstanding -= 1;
-                                    self.remove_request(p.get_header().common_header.stamp);
+                let curr = cycles::rdtsc();
+
+                // With the adaptive ratio both tiers are in flight at once, so
+                // responses are demultiplexed purely by opcode: invoke() replies
+                // belong to the server tier, while get() replies whose stamp is
+                // tracked in `native_state` belong to the client-executed tier.
+                match parse_rpc_opcode(&packet) {
+                    // The response corresponds to an invoke() RPC.
+                    OpCode::SandstormInvokeRpc => {
+                        let p = packet.parse_header::<InvokeResponse>();
+                        match p.get_header().common_header.status {
+                            // If the status is StatusOk then add the stamp to the latencies and
+                            // free the packet.
+                            RpcStatus::StatusOk => {
+                                let stamp = p.get_header().common_header.stamp;
+
+                                // With hedging on, the first copy to return wins;
+                                // a duplicate for an id already retired is a
+                                // straggler and is ignored.
+                                if self.hedge {
+                                    match self.inflight.borrow_mut().remove(&stamp) {
+                                        Some(req) => {
+                                            if req.hedged {
+                                                self.hedge_served += 1;
+                                            }
+                                        }
+                                        None => {
+                                            p.free_packet();
+                                            continue;
+                                        }
+                                    }
                                 }
 
-                                // If the status is StatusPushback then compelete the task, add the
-                                // stamp to the latencies, and free the packet.
-                                RpcStatus::StatusPushback => {
-                                    let records = p.get_payload();
-                                    let hdr = &p.get_header();
-                                    let timestamp = hdr.common_header.stamp;
-
-                                    // Create task and run the generator.
-                                    match self.manager.borrow_mut().remove(&timestamp) {
-                                        Some(mut manager) => {
-                                            manager.create_generator(Arc::clone(&self.sender));
-                                            manager.update_rwset(records);
-                                            self.waiting.write().push_back(manager);
-                                        }
+                                self.recvd += 1;
+                                let latency = curr - stamp;
+                                self.latencies.record(latency);
+                                self.note_latency(latency);
+                                self.outstanding -= 1;
+                                self.remove_request(stamp);
+                            }
 
+                            // If the status is StatusPushback then compelete the task, add the
+                            // stamp to the latencies, and free the packet.
+                            RpcStatus::StatusPushback => {
+                                let records = p.get_payload();
+                                let hdr = &p.get_header();
+                                let timestamp = hdr.common_header.stamp;
+
+                                let tenant = hdr.common_header.tenant;
+
+                                // Drop a duplicate response for an id that a
+                                // faster copy already retired.
+                                if self.hedge {
+                                    match self.inflight.borrow_mut().remove(&timestamp) {
+                                        Some(req) => {
+                                            if req.hedged {
+                                                self.hedge_served += 1;
+                                            }
+                                        }
                                         None => {
-                                            info!("No manager with {} timestamp", timestamp);
+                                            p.free_packet();
+                                            continue;
                                         }
                                     }
-                                    self.latencies.push(cycles::rdtsc() - timestamp);
-                                    self.outstanding -= 1;
-                                    self.recvd += 1;
                                 }
 
-                                _ => {}
-                            }
-                            p.free_packet();
-                        }
+                                // Create task and run the generator.
+                                match self.manager.borrow_mut().remove(&timestamp) {
+                                    Some(mut manager) => {
+                                        manager.create_generator(Arc::clone(&self.sender));
+                                        manager.update_rwset(records);
+                                        self.waiting.write().push(tenant, manager);
+                                    }
 
-                        // The response corresponds to a get() or put() RPC.
-                        // The opcode on the response identifies the RPC type.
-                        OpCode::SandstormGetRpc => {
-                            let p = packet.parse_header::<GetResponse>();
-                            self.latencies
-                                .push(curr - p.get_header().common_header.stamp);
-                            unsafe {
-                                if self
-                                    .manager
-                                    .borrow()
-                                    .contains_key(&p.get_header().common_header.stamp)
-                                {
-                                    let manager = self
-                                        .manager
-                                        .borrow_mut()
-                                        .remove(&p.get_header().common_header.stamp);
-                                    if let Some(mut manager) = manager {
-                                        self.waiting.write().push_back(manager);
+                                    None => {
+                                        info!("No manager with {} timestamp", timestamp);
                                     }
                                 }
+                                let latency = cycles::rdtsc() - timestamp;
+                                self.latencies.record(latency);
+                                self.note_latency(latency);
+                                self.outstanding -= 1;
+                                self.recvd += 1;
                             }
-                            p.free_packet();
-                        }
 
-                        OpCode::SandstormPutRpc => {
-                            let p = packet.parse_header::<PutResponse>();
-                            self.latencies
-                                .push(curr - p.get_header().common_header.stamp);
-                            p.free_packet();
+                            _ => {}
                         }
-
-                        _ => packet.free_packet(),
+                        p.free_packet();
                     }
-                } else {
-                    //The extension is executed locally on the client side.
-                    match parse_rpc_opcode(&packet) {
-                        OpCode::SandstormGetRpc => {
-                            let p = packet.parse_header::<GetResponse>();
-                            let timestamp = p.get_header().common_header.stamp;
+
+                    // The response corresponds to a get() RPC. A stamp tracked in
+                    // `native_state` identifies a client-executed request; any
+                    // other get() reply feeds a pushed-back invoke() task.
+                    OpCode::SandstormGetRpc => {
+                        let p = packet.parse_header::<GetResponse>();
+                        let timestamp = p.get_header().common_header.stamp;
+
+                        let native = self.native_state.borrow().contains_key(&timestamp);
+                        if native {
                             let count = *self.native_state.borrow().get(&timestamp).unwrap();
                             if count == NUM_OPS {
                                 self.recvd += 1;
                                 let init = p.get_payload()[0];
                                 let mul = self.mul(init, NUM_MUL);
-                                self.latencies.push(cycles::rdtsc() - timestamp - mul);
+                                let latency = cycles::rdtsc() - timestamp - mul;
+                                self.latencies.record(latency);
+                                self.note_latency(latency);
                                 self.native_state.borrow_mut().remove(&timestamp);
                                 self.outstanding -= 1;
                             } else {
@@ -526,28 +1236,101 @@ where
                                     *count += 1;
                                 }
                             }
-                            p.free_packet();
+                        } else {
+                            let latency = curr - timestamp;
+                            self.latencies.record(latency);
+                            self.note_latency(latency);
+                            let present = self.manager.borrow().contains_key(&timestamp);
+                            if present {
+                                let tenant = p.get_header().common_header.tenant;
+                                let manager = self.manager.borrow_mut().remove(&timestamp);
+                                if let Some(manager) = manager {
+                                    self.waiting.write().push(tenant, manager);
+                                }
+                            }
                         }
+                        p.free_packet();
+                    }
 
-                        _ => packet.free_packet(),
+                    OpCode::SandstormPutRpc => {
+                        let p = packet.parse_header::<PutResponse>();
+                        let latency = curr - p.get_header().common_header.stamp;
+                        self.latencies.record(latency);
+                        self.note_latency(latency);
+                        p.free_packet();
                     }
+
+                    _ => packet.free_packet(),
                 }
             }
         }
 
-        // The moment all response packets have been received, set the value of the
-        // stop timestamp so that throughput can be estimated later.
+        // Step the adaptive invoke/native ratio controller at epoch boundaries.
+        self.adapt();
+
+        // Periodically emit an online latency snapshot from the master thread so
+        // that tails are visible mid-run rather than only at teardown.
+        if self.master && self.recvd > 0 && self.recvd % 2_000_000 == 0 {
+            info!(
+                "online p50 {:.1} p99 {:.1} p999 {:.1} ns outstanding {}/{}",
+                cycles::to_seconds(self.latencies.percentile(0.50)) * 1e9,
+                cycles::to_seconds(self.latencies.percentile(0.99)) * 1e9,
+                cycles::to_seconds(self.latencies.percentile(0.999)) * 1e9,
+                self.outstanding,
+                self.max_outstanding
+            );
+        }
+
+        // The moment all response packets have been received, publish this
+        // core's finalized histogram so `main()` can merge across cores.
         if self.responses <= self.recvd {
+            self.publish_histogram();
+        }
+    }
+
+    /// Publishes this core's latency histogram into the shared `CORE_HISTOGRAMS`
+    /// map that `main()` merges after shutdown, stamping the stop time on the
+    /// first call. Idempotent: only the first call per core takes effect.
+    ///
+    /// We publish from here (on completion, or when the run is torn down) rather
+    /// than from `Drop`, because the scheduler-owned pipeline tasks are not
+    /// dropped until `net_context` leaves scope at the very end of `main()` —
+    /// i.e. after the merge — so a `Drop`-time insert would land too late and the
+    /// merge would see an empty map. Publishing on teardown (not only on reaching
+    /// the per-core quota) ensures every core contributes, not just the fastest
+    /// one that trips the global `FINISHED` flag first.
+    fn publish_histogram(&mut self) {
+        if self.published {
+            return;
+        }
+        if self.stop == 0 {
             self.stop = cycles::rdtsc();
         }
+        core_histograms()
+            .write()
+            .insert(self.core, self.latencies.clone());
+        self.published = true;
     }
 
     fn execute_task(&mut self) {
-        let manager = self.waiting.write().pop_front();
-        if let Some(mut manager) = manager {
+        // Pick the next class to run under deficit round-robin, then pop its
+        // front task.
+        let class = match self.waiting.write().select() {
+            Some(class) => class,
+            None => return,
+        };
+        let popped = self.waiting.write().pop(class);
+
+        if let Some((tenant, mut manager)) = popped {
             let (taskstate, _time) = manager.execute_task();
+
+            // Charge the measured cycle cost against the class's credit and
+            // accumulate it into the per-tenant accounting.
+            self.waiting.write().charge(class, _time);
+            *self.tenant_cycles.borrow_mut().entry(tenant).or_insert(0) += _time;
+
             if taskstate == YIELDED {
-                self.waiting.write().push_back(manager);
+                self.waiting.write().requeue(tenant, manager);
             } else if taskstate == WAITING {
                 self.manager.borrow_mut().insert(manager.get_id(), manager);
             } else if taskstate == COMPLETED && cfg!(feature = "execution") {
@@ -579,24 +1362,62 @@ where
 
         // Calculate & print median & tail latency only on the master thread.
         if self.master {
-            self.latencies.sort();
-
-            let m;
-            let t = self.latencies[(self.latencies.len() * 99) / 100];
-            match self.latencies.len() % 2 {
-                0 => {
-                    let n = self.latencies.len();
-                    m = (self.latencies[n / 2] + self.latencies[(n / 2) + 1]) / 2;
-                }
+            let m = self.latencies.percentile(0.50);
+            let t = self.latencies.percentile(0.99);
+            let t9 = self.latencies.percentile(0.999);
 
-                _ => m = self.latencies[self.latencies.len() / 2],
-            }
+            println!(
+                ">>> {} {} {}",
+                cycles::to_seconds(m) * 1e9,
+                cycles::to_seconds(t) * 1e9,
+                cycles::to_seconds(t9) * 1e9
+            );
 
+            // Full fixed-memory tail report: p50/p90/p99/p99.9/p99.99 plus
+            // min/max/mean, all served from the bounded histogram without a sort.
             println!(
-                ">>> {} {}",
+                ">>> pctl p50 {} p90 {} p99 {} p999 {} p9999 {} min {} max {} mean {}",
                 cycles::to_seconds(m) * 1e9,
-                cycles::to_seconds(t) * 1e9
+                cycles::to_seconds(self.latencies.percentile(0.90)) * 1e9,
+                cycles::to_seconds(t) * 1e9,
+                cycles::to_seconds(t9) * 1e9,
+                cycles::to_seconds(self.latencies.percentile(0.9999)) * 1e9,
+                cycles::to_seconds(self.latencies.min()) * 1e9,
+                cycles::to_seconds(self.latencies.max()) * 1e9,
+                cycles::to_seconds(self.latencies.mean()) * 1e9
             );
+
+            // In open-loop mode, report the median and tail queueing delay
+            // (actual minus scheduled send time) so that saturation shows up as
+            // growing offered-vs-achieved load.
+            if self.open_loop {
+                println!(
+                    ">>> queueing {} {} {}",
+                    cycles::to_seconds(self.queueing.percentile(0.50)) * 1e9,
+                    cycles::to_seconds(self.queueing.percentile(0.99)) * 1e9,
+                    cycles::to_seconds(self.queueing.percentile(0.999)) * 1e9
+                );
+            }
+
+            // Report hedging activity: how many duplicates were fired and what
+            // fraction of responses belonged to requests that had been hedged.
+            if self.hedge {
+                let served_frac = if self.recvd > 0 {
+                    self.hedge_served as f64 / self.recvd as f64
+                } else {
+                    0.0
+                };
+                println!(
+                    ">>> hedge fired {} served {} frac {:.4}",
+                    self.hedge_fired, self.hedge_served, served_frac
+                );
+            }
+
+            // Report per-tenant cycles spent executing pushed-back tasks so the
+            // fairness of the deficit-round-robin schedule can be assessed.
+            for (tenant, cycles) in self.tenant_cycles.borrow().iter() {
+                println!("tenant {} class {} cycles {}", tenant, RunQueue::class_of(*tenant), cycles);
+            }
         }
     }
 }
@@ -611,7 +1432,12 @@ where
         self.send();
         self.recv();
         self.execute_task();
-        if self.finished == true {
+        // Tear down when this core has drained its own quota, or when any other
+        // core has already finished and flipped the global flag. In both cases
+        // publish this core's histogram first so the cluster-wide merge in
+        // `main()` includes every pipeline, not just the fastest one.
+        if self.finished || unsafe { FINISHED } {
+            self.publish_histogram();
             unsafe { FINISHED = true }
             return;
         }
@@ -625,8 +1451,9 @@ where
 fn setup_send_recv<S>(
     ports: Vec<CacheAligned<PortQueue>>,
     scheduler: &mut S,
-    _core: i32,
+    core: i32,
     master: bool,
+    reqs: u64,
     config: &config::ClientConfig,
     masterservice: Arc<Master>,
 ) where
@@ -637,14 +1464,17 @@ fn setup_send_recv<S>(
         std::process::exit(1);
     }
 
-    // Add the receiver to a netbricks pipeline.
+    // Add the receiver to a netbricks pipeline. This pipeline sends and expects
+    // `reqs` requests: its share of the global budget split across the client
+    // cores.
     match scheduler.add_task(PushbackRecvSend::new(
         ports[0].clone(),
-        34 * 1000 * 1000 as u64,
+        reqs,
         master,
+        core,
         config,
         ports[0].clone(),
-        config.num_reqs as u64,
+        reqs,
         config.server_udp_ports as u16,
         masterservice,
     )) {
@@ -682,31 +1512,36 @@ fn main() {
     // Setup the client pipeline.
     net_context.start_schedulers();
 
-    // The core id's which will run the sender and receiver threads.
-    // XXX The following array heavily depend on the set of cores
-    // configured in setup.rs
-    let senders_receivers = [0, 1, 2, 3, 4, 5, 6, 7];
-    assert!(senders_receivers.len() == 8);
-
-    // Setup 8 senders, and receivers.
-    for i in 0..8 {
+    // The core id's which will run the sender and receiver threads, read from
+    // the config so the client can be scaled from 1 to many cores without a
+    // recompile.
+    // XXX These core ids must be a subset of the cores configured in setup.rs.
+    let senders_receivers = &config.client_cores;
+    assert!(
+        !senders_receivers.is_empty(),
+        "client_cores must list at least one core"
+    );
+
+    // Split the global request budget evenly across the client cores.
+    let per_core_reqs = config.num_reqs as u64 / senders_receivers.len() as u64;
+
+    // Setup one sender/receiver pipeline per configured core, electing the first
+    // listed core as the measurement master.
+    for (i, &core_id) in senders_receivers.iter().enumerate() {
         // First, retrieve a tx-rx queue pair from Netbricks
         let port = net_context
             .rx_queues
-            .get(&senders_receivers[i])
+            .get(&core_id)
             .expect("Failed to retrieve network port!")
             .clone();
 
-        let mut master = false;
-        if i == 0 {
-            master = true;
-        }
+        let master = i == 0;
 
         let master_service = Arc::clone(&masterservice);
         // Setup the receive and transmit side.
         net_context
             .add_pipeline_to_core(
-                senders_receivers[i],
+                core_id,
                 Arc::new(
                     move |_ports, sched: &mut StandaloneScheduler, core: i32, _sibling| {
                         setup_send_recv(
@@ -714,6 +1549,7 @@ fn main() {
                             sched,
                             core,
                             master,
+                            per_core_reqs,
                             &config::ClientConfig::load(),
                             Arc::clone(&master_service),
                         )
@@ -738,6 +1574,28 @@ fn main() {
 
     // Stop the client.
     net_context.stop();
+
+    // Merge the per-core histograms (bucket-wise) into one and report the
+    // cluster-wide tail, so the percentiles cover every pipeline's traffic
+    // rather than only the master core's.
+    let merged = {
+        let mut acc = Histogram::new();
+        for hist in core_histograms().read().values() {
+            acc.merge(hist);
+        }
+        acc
+    };
+    println!(
+        ">>> merged p50 {} p90 {} p99 {} p999 {} p9999 {} min {} max {} mean {}",
+        cycles::to_seconds(merged.percentile(0.50)) * 1e9,
+        cycles::to_seconds(merged.percentile(0.90)) * 1e9,
+        cycles::to_seconds(merged.percentile(0.99)) * 1e9,
+        cycles::to_seconds(merged.percentile(0.999)) * 1e9,
+        cycles::to_seconds(merged.percentile(0.9999)) * 1e9,
+        cycles::to_seconds(merged.min()) * 1e9,
+        cycles::to_seconds(merged.max()) * 1e9,
+        cycles::to_seconds(merged.mean()) * 1e9
+    );
 }
 
 #[cfg(test)]
@@ -745,6 +1603,7 @@ mod test {
     use std;
     use std::collections::HashMap;
     use std::mem::transmute;
+    use super::Histogram;
     use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::{Arc, Mutex};
     use std::thread;
@@ -797,6 +1656,69 @@ mod test {
         );
     }
 
+    // Percentiles must be monotonically non-decreasing in q, min/max must track
+    // the recorded extremes, and the median must land near the true one.
+    #[test]
+    fn histogram_percentiles_monotonic() {
+        let mut h = Histogram::new();
+        for v in 1..10_001u64 {
+            h.record(v);
+        }
+
+        let qs = [0.0, 0.5, 0.9, 0.99, 0.999, 1.0];
+        let mut last = 0u64;
+        for &q in qs.iter() {
+            let p = h.percentile(q);
+            assert!(p >= last, "percentile decreased at q={}: {} < {}", q, p, last);
+            last = p;
+        }
+
+        assert!(h.min() >= 1);
+        assert!(h.max() >= 10_000);
+        // The median of 1..=10000 should land near 5000 within the histogram's
+        // ~1% bucket resolution.
+        let p50 = h.percentile(0.5) as f64;
+        assert!((p50 - 5000.0).abs() / 5000.0 < 0.02, "p50={}", p50);
+    }
+
+    // A recorded value must be reconstructed by a top-percentile query to within
+    // the histogram's bounded relative error (never above the true value).
+    #[test]
+    fn histogram_value_roundtrip() {
+        for &v in &[1u64, 7, 128, 1000, 1_000_000, 1 << 40] {
+            let mut h = Histogram::new();
+            h.record(v);
+            let got = h.percentile(1.0);
+            assert!(got <= v, "reconstructed {} exceeds recorded {}", got, v);
+            assert!(
+                got as f64 >= v as f64 * (1.0 - 1.0 / 128.0),
+                "reconstructed {} too far below recorded {}",
+                got,
+                v
+            );
+        }
+    }
+
+    // Merging adds bucket counts and combines the scalar aggregates.
+    #[test]
+    fn histogram_merge_adds_counts() {
+        let mut a = Histogram::new();
+        let mut b = Histogram::new();
+        for v in 1..101u64 {
+            a.record(v);
+        }
+        for v in 101..201u64 {
+            b.record(v);
+        }
+        a.merge(&b);
+
+        assert_eq!(a.min(), 1);
+        assert!(a.max() >= 200);
+        // Median of the merged 1..=200 set lands near 100.
+        let p50 = a.percentile(0.5) as f64;
+        assert!((p50 - 100.0).abs() / 100.0 < 0.05, "p50={}", p50);
+    }
+
     // Convert a key to u32 assuming little endian.
     fn convert_key(key: &[u8]) -> u32 {
         assert_eq!(4, key.len());